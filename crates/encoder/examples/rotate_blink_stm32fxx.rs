@@ -7,7 +7,7 @@ use crate::hal::{pac, prelude::*}; // STM32F1 specific functions // When a panic
 
 use cortex_m_rt::entry;
 use embedded_hal::digital::v2::OutputPin;
-use encoder::{Action};
+use encoder::{Action, PinPolarity};
 #[allow(unused_imports)]
 #[allow(clippy::single_component_path_imports)]
 use panic_halt;
@@ -38,7 +38,9 @@ fn main() -> ! {
     let mut ccw_t = 0;
     let mut click_t = 0;
 
-    let mut encoder = encoder::EncoderInfallible::new(s1_pin, s2_pin, key_pin);
+    // Пины подтянуты к земле (into_pull_down_input), поэтому логика активно-высокая.
+    let mut encoder =
+        encoder::EncoderInfallible::new(s1_pin, s2_pin, key_pin, PinPolarity::ActiveHigh);
     loop {
         let action = encoder.update();
 
@@ -27,6 +27,42 @@ where
     BPin(B::Error),
 }
 
+// ----------------
+// # PinPolarity #
+// ----------------
+
+/// Полярность пинов: как уровень на пине отображается в логическое состояние.
+///
+/// [ActiveLow](PinPolarity::ActiveLow) соответствует подтяжке к питанию (покой —
+/// высокий уровень, нажатие — низкий), [ActiveHigh](PinPolarity::ActiveHigh) —
+/// подтяжке к земле (покой — низкий уровень, нажатие — высокий).
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum PinPolarity {
+    ActiveHigh,
+    ActiveLow,
+}
+
+// --------
+// # Mode #
+// --------
+
+/// Режим декодирования квадратурного сигнала.
+///
+/// Энкодеры различаются числом переходов на один детент: [FullStep](Mode::FullStep)
+/// даёт один шаг на полный цикл (±4 перехода), а [HalfStep](Mode::HalfStep) — два
+/// (±2 перехода).
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum Mode {
+    FullStep,
+    HalfStep,
+}
+
+/// Стандартная таблица переходов квадратурного энкодера.
+///
+/// Индексируется как `(prev_ab << 2) | curr_ab`; значение — вклад перехода
+/// (`-1`/`0`/`+1`) в накопитель.
+const STATES: [i8; 16] = [0, -1, 1, 0, 1, 0, 0, -1, -1, 0, 0, 1, 0, 1, -1, 0];
+
 // ----------
 // # Rotary #
 // ----------
@@ -36,6 +72,9 @@ pub struct Rotary<A, B> {
     a_pin: A,
     b_pin: B,
     ab_history: u8, // содержит 4 состояния пинов a и b
+    mode: Mode,
+    polarity: PinPolarity,
+    accum: i8, // накопитель переходов до достижения одного детента
 }
 
 impl<A, B> Rotary<A, B>
@@ -43,41 +82,190 @@ where
     A: InputPin,
     B: InputPin,
 {
-    pub fn new(a_pin: A, b_pin: B) -> Self {
-        Self {
+    pub fn new(a_pin: A, b_pin: B, polarity: PinPolarity) -> Self {
+        Self::new_with_mode(a_pin, b_pin, Mode::FullStep, polarity)
+    }
+
+    /// Создаёт энкодер с явно заданным режимом декодирования.
+    pub fn new_with_mode(a_pin: A, b_pin: B, mode: Mode, polarity: PinPolarity) -> Self {
+        let mut rotary = Self {
             a_pin,
             b_pin,
-            ab_history: 0b11111111,
+            ab_history: 0,
+            mode,
+            polarity,
+            accum: 0,
+        };
+        rotary.reset_history();
+        rotary
+    }
+
+    /// Уровень пинов в состоянии покоя (оба пина «отпущены»).
+    #[inline(always)]
+    fn rest_ab(&self) -> u8 {
+        match self.polarity {
+            PinPolarity::ActiveLow => 0b11,
+            PinPolarity::ActiveHigh => 0b00,
         }
     }
 
+    /// Сбрасывает историю состояний в состояние покоя и обнуляет накопитель.
+    fn reset_history(&mut self) {
+        let rest = self.rest_ab();
+        self.ab_history = (rest << 6) | (rest << 4) | (rest << 2) | rest;
+        self.accum = 0;
+    }
+
     /// Возвращает направление вращение энкодера
     pub fn update(&mut self) -> Result<Direction, RotaryError<A, B>> {
         let a_high = self.a_pin.is_high().map_err(RotaryError::APin)?;
         let b_high = self.b_pin.is_high().map_err(RotaryError::BPin)?;
 
         let as_num = |b| if b { 1u8 } else { 0u8 };
-        let bits_state = (as_num(a_high) << 1) | as_num(b_high);
+        let curr_ab = (as_num(a_high) << 1) | as_num(b_high);
+        let prev_ab = self.ab_history & 0b11;
+
+        // Накапливаем вклад перехода по таблице и запоминаем новое состояние.
+        // Значение ограничено порогом, поэтому быстрая прокрутка с редким
+        // опросом не может переполнить `i8`.
+        let threshold: i8 = match self.mode {
+            Mode::FullStep => 4,
+            Mode::HalfStep => 2,
+        };
+        self.accum = self
+            .accum
+            .saturating_add(STATES[((prev_ab << 2) | curr_ab) as usize])
+            .clamp(-threshold, threshold);
+        self.ab_history = (self.ab_history << 2) | curr_ab;
+
+        // Детент фиксируется на границе состояний: в полношаговом режиме это
+        // состояние покоя, в полушаговом — любое из двух устойчивых (`11`/`00`),
+        // так как такой энкодер имеет детент на каждом из них.
+        let at_boundary = match self.mode {
+            Mode::FullStep => curr_ab == self.rest_ab(),
+            Mode::HalfStep => curr_ab == 0b11 || curr_ab == 0b00,
+        };
+        let direction = if at_boundary && self.accum >= threshold {
+            self.accum = 0;
+            Direction::Cw
+        } else if at_boundary && self.accum <= -threshold {
+            self.accum = 0;
+            Direction::Ccw
+        } else {
+            Direction::None
+        };
+
+        Ok(direction)
+    }
+
+    /// Изменяемые ссылки на пины `a` и `b`.
+    ///
+    /// Позволяет, например, снять флаг прерывания EXTI прямо в обработчике,
+    /// не отдавая владение энкодером.
+    pub fn pins_mut(&mut self) -> (&mut A, &mut B) {
+        (&mut self.a_pin, &mut self.b_pin)
+    }
+
+    /// Возвращает владение пинами, поглощая энкодер.
+    pub fn release(self) -> (A, B) {
+        (self.a_pin, self.b_pin)
+    }
+}
+
+// ------------------
+// # RotaryVelocity #
+// ------------------
+
+/// Скорость вращения энкодера в диапазоне `0.0..=1.0`.
+#[cfg(feature = "velocity")]
+pub type Velocity = f32;
 
-        // Обновляем историю состояний энкодера
-        if (self.ab_history & 0b11) != bits_state {
-            self.ab_history = (self.ab_history << 2) | bits_state;
+/// Энкодер, который кроме направления сообщает и скорость вращения.
+///
+/// Источник времени (счётчик миллисекунд) передаётся вызывающей стороной в
+/// [update], поэтому тип остаётся `no_std` и не зависит от конкретного HAL.
+#[cfg(feature = "velocity")]
+pub struct RotaryVelocity<A, B> {
+    rotary: Rotary<A, B>,
+    velocity: Velocity,
+    last_increment_ms: Option<u32>, // засеивается первым вызовом update
+    inc_factor: Velocity,
+    dec_factor: Velocity,
+    decay_interval_ms: u32,
+}
+
+#[cfg(feature = "velocity")]
+impl<A, B> RotaryVelocity<A, B>
+where
+    A: InputPin,
+    B: InputPin,
+{
+    pub fn new(a_pin: A, b_pin: B, polarity: PinPolarity) -> Self {
+        Self {
+            rotary: Rotary::new(a_pin, b_pin, polarity),
+            velocity: 0.0,
+            last_increment_ms: None,
+            inc_factor: 0.2,
+            dec_factor: 0.01,
+            decay_interval_ms: 100,
         }
+    }
 
-        // match по истории с текущим состоянием
-        let direction = match self.ab_history {
-            0b10000111 => {
-                self.ab_history = 0xFF;
-                Direction::Cw
-            }
-            0b01001011 => {
-                self.ab_history = 0xFF;
-                Direction::Ccw
+    /// Возвращает направление вращения и текущую скорость.
+    ///
+    /// Если на этом тике был зафиксирован шаг, скорость увеличивается на
+    /// `inc_factor` (с насыщением до `1.0`); иначе она затухает на `dec_factor`
+    /// один раз за каждое окно `decay_interval_ms`, что делает затухание
+    /// независимым от частоты опроса.
+    pub fn update(&mut self, now_ms: u32) -> Result<(Direction, Velocity), RotaryError<A, B>> {
+        let direction = self.rotary.update()?;
+
+        if direction != Direction::None {
+            self.velocity = (self.velocity + self.inc_factor).min(1.0);
+            self.last_increment_ms = Some(now_ms);
+        } else {
+            // На первом вызове засеиваем точку отсчёта текущим временем, чтобы
+            // не насчитать огромный интервал от нуля.
+            let last = *self.last_increment_ms.get_or_insert(now_ms);
+            let intervals = now_ms.wrapping_sub(last) / self.decay_interval_ms;
+            if intervals > 0 {
+                // Больше нескольких шагов не нужно: скорость насыщается в `0.0`,
+                // поэтому ограничиваем число итераций необходимым минимумом.
+                let needed = if self.dec_factor > 0.0 {
+                    (self.velocity / self.dec_factor) as u32 + 1
+                } else {
+                    0
+                };
+                for _ in 0..intervals.min(needed) {
+                    self.velocity = (self.velocity - self.dec_factor).max(0.0);
+                }
+                self.last_increment_ms =
+                    Some(last.wrapping_add(intervals * self.decay_interval_ms));
             }
-            _ => Direction::None,
-        };
+        }
 
-        Ok(direction)
+        Ok((direction, self.velocity))
+    }
+
+    /// Текущая скорость вращения.
+    #[inline(always)]
+    pub fn velocity(&self) -> Velocity {
+        self.velocity
+    }
+
+    /// Прирост скорости на один зафиксированный шаг.
+    pub fn set_velocity_inc_factor(&mut self, inc_factor: Velocity) {
+        self.inc_factor = inc_factor;
+    }
+
+    /// Затухание скорости за одно окно `decay_interval_ms`.
+    pub fn set_velocity_dec_factor(&mut self, dec_factor: Velocity) {
+        self.dec_factor = dec_factor;
+    }
+
+    /// Длительность окна затухания в миллисекундах.
+    pub fn set_decay_interval_ms(&mut self, decay_interval_ms: u32) {
+        self.decay_interval_ms = decay_interval_ms;
     }
 }
 
@@ -93,8 +281,8 @@ where
     A: InputPin<Error = Infallible>,
     B: InputPin<Error = Infallible>,
 {
-    pub fn new(a_pin: A, b_pin: B) -> Self {
-        Self(Rotary::new(a_pin, b_pin))
+    pub fn new(a_pin: A, b_pin: B, polarity: PinPolarity) -> Self {
+        Self(Rotary::new(a_pin, b_pin, polarity))
     }
 
     /// Возвращает направление вращение энкодера
@@ -204,6 +392,7 @@ pub struct Encoder<A, B, K> {
     rotary: Rotary<A, B>,
 
     k_pin: K,
+    polarity: PinPolarity,
     pressed: bool,
     rotated_after_key_change: bool,
 }
@@ -214,11 +403,12 @@ where
     B: InputPin,
     K: InputPin,
 {
-    pub fn new(a_pin: A, b_pin: B, k_pin: K) -> Self {
-        let rotary = Rotary::new(a_pin, b_pin);
+    pub fn new(a_pin: A, b_pin: B, k_pin: K, polarity: PinPolarity) -> Self {
+        let rotary = Rotary::new(a_pin, b_pin, polarity);
         Self {
             rotary,
             k_pin,
+            polarity,
             pressed: false,
             rotated_after_key_change: false,
         }
@@ -231,7 +421,10 @@ where
         let k_high = self.k_pin.is_high().map_err(EncoderError::KPin)?;
 
         // Получаем текущее состояние с учетом старого
-        let pressed = !k_high;
+        let pressed = match self.polarity {
+            PinPolarity::ActiveLow => !k_high,
+            PinPolarity::ActiveHigh => k_high,
+        };
         let was_pressed = self.pressed;
         let just_key_changed = pressed != was_pressed;
         let rotated_before_key_change = just_key_changed & self.rotated_after_key_change;
@@ -242,8 +435,7 @@ where
         self.pressed = pressed;
         self.rotated_after_key_change = rotated_after_key_change;
         if just_key_changed {
-            let initial = if pressed { 0x00 } else { 0xFF };
-            self.rotary.ab_history = initial;
+            self.rotary.reset_history();
         }
 
         // Возвращаем текущее состояние
@@ -270,6 +462,21 @@ where
         };
         Ok(action)
     }
+
+    /// Изменяемые ссылки на пины `a`, `b` и кнопки `k`.
+    ///
+    /// Позволяет снять флаг прерывания EXTI прямо в обработчике, не отдавая
+    /// владение энкодером.
+    pub fn pins_mut(&mut self) -> (&mut A, &mut B, &mut K) {
+        let (a_pin, b_pin) = self.rotary.pins_mut();
+        (a_pin, b_pin, &mut self.k_pin)
+    }
+
+    /// Возвращает владение пинами, поглощая энкодер.
+    pub fn release(self) -> (A, B, K) {
+        let (a_pin, b_pin) = self.rotary.release();
+        (a_pin, b_pin, self.k_pin)
+    }
 }
 
 // ---------------------
@@ -284,8 +491,8 @@ where
     B: InputPin<Error = Infallible>,
     K: InputPin<Error = Infallible>,
 {
-    pub fn new(a_pin: A, b_pin: B, k_pin: K) -> Self {
-        Self(Encoder::new(a_pin, b_pin, k_pin))
+    pub fn new(a_pin: A, b_pin: B, k_pin: K, polarity: PinPolarity) -> Self {
+        Self(Encoder::new(a_pin, b_pin, k_pin, polarity))
     }
 
     /// Возвращает "Сырое" представление состояния энкодера.
@@ -299,3 +506,222 @@ where
         unsafe { self.0.update().unwrap_unchecked() }
     }
 }
+
+// ------------------
+// # ExtendedAction #
+// ------------------
+
+/// Расширенный набор действий с классификацией нажатий по времени.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum ExtendedAction {
+    None,
+    Cw,
+    Ccw,
+    CwPressed,
+    CcwPressed,
+    SingleClick,
+    DoubleClick,
+    LongPress,
+}
+
+// -----------------
+// # ClickDetector #
+// -----------------
+
+/// Надстройка над [Encoder], различающая одиночный, двойной клик и удержание.
+///
+/// Источник времени (счётчик миллисекунд) передаётся вызывающей стороной в
+/// [update], поэтому тип остаётся `no_std` и HAL-агностичным. Пороги
+/// настраиваются сеттерами без перекомпиляции.
+pub struct ClickDetector<A, B, K> {
+    encoder: Encoder<A, B, K>,
+    press_start_ms: u32,
+    single_since_ms: u32,
+    single_pending: bool,
+    suppress_release: bool,
+    long_press_ms: u32,
+    double_click_window_ms: u32,
+}
+
+impl<A, B, K> ClickDetector<A, B, K>
+where
+    A: InputPin,
+    B: InputPin,
+    K: InputPin,
+{
+    pub fn new(a_pin: A, b_pin: B, k_pin: K, polarity: PinPolarity) -> Self {
+        Self {
+            encoder: Encoder::new(a_pin, b_pin, k_pin, polarity),
+            press_start_ms: 0,
+            single_since_ms: 0,
+            single_pending: false,
+            suppress_release: false,
+            long_press_ms: 500,
+            double_click_window_ms: 300,
+        }
+    }
+
+    /// Порог удержания для [LongPress](ExtendedAction::LongPress).
+    pub fn set_long_press_ms(&mut self, long_press_ms: u32) {
+        self.long_press_ms = long_press_ms;
+    }
+
+    /// Окно ожидания второго нажатия для [DoubleClick](ExtendedAction::DoubleClick).
+    pub fn set_double_click_window_ms(&mut self, double_click_window_ms: u32) {
+        self.double_click_window_ms = double_click_window_ms;
+    }
+
+    /// Возвращает действие энкодера с учётом временной классификации нажатий.
+    pub fn update(&mut self, now_ms: u32) -> Result<ExtendedAction, EncoderError<A, B, K>> {
+        let raw = self.encoder.update_raw()?;
+
+        // Вращение имеет приоритет и отражает логику [Encoder::update].
+        match (raw.direction, raw.pressed) {
+            (Direction::Cw, true) => return Ok(ExtendedAction::CwPressed),
+            (Direction::Cw, false) => return Ok(ExtendedAction::Cw),
+            (Direction::Ccw, true) => return Ok(ExtendedAction::CcwPressed),
+            (Direction::Ccw, false) => return Ok(ExtendedAction::Ccw),
+            _ => {}
+        }
+
+        if raw.just_key_changed {
+            if raw.pressed {
+                self.press_start_ms = now_ms;
+                // Вращение до нажатия подавляет все клики этой серии.
+                self.suppress_release = raw.rotated_before_key_change;
+                if self.single_pending
+                    && !raw.rotated_before_key_change
+                    && now_ms.wrapping_sub(self.single_since_ms) < self.double_click_window_ms
+                {
+                    self.single_pending = false;
+                    self.suppress_release = true;
+                    return Ok(ExtendedAction::DoubleClick);
+                }
+            } else {
+                let held = now_ms.wrapping_sub(self.press_start_ms);
+                if self.suppress_release || raw.rotated_before_key_change {
+                    self.suppress_release = false;
+                } else if held >= self.long_press_ms {
+                    return Ok(ExtendedAction::LongPress);
+                } else {
+                    self.single_pending = true;
+                    self.single_since_ms = now_ms;
+                }
+            }
+        } else if self.single_pending
+            && now_ms.wrapping_sub(self.single_since_ms) >= self.double_click_window_ms
+        {
+            // Окно двойного клика истекло — отдаём отложенный одиночный клик.
+            self.single_pending = false;
+            return Ok(ExtendedAction::SingleClick);
+        }
+
+        Ok(ExtendedAction::None)
+    }
+
+    /// Изменяемые ссылки на пины `a`, `b` и кнопки `k`.
+    pub fn pins_mut(&mut self) -> (&mut A, &mut B, &mut K) {
+        self.encoder.pins_mut()
+    }
+
+    /// Возвращает владение пинами, поглощая детектор.
+    pub fn release(self) -> (A, B, K) {
+        self.encoder.release()
+    }
+}
+
+// ---------------
+// # OutOfRange #
+// ---------------
+
+/// Поведение счётчика при выходе позиции за пределы диапазона.
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum OutOfRange {
+    /// Насыщение на концах диапазона.
+    Clamp,
+    /// Переход с `max` на `min` и обратно.
+    Wrap,
+}
+
+// -----------
+// # Counter #
+// -----------
+
+/// Накопитель позиции по потоку [Direction].
+///
+/// Каждый [Cw](Direction::Cw) добавляет `step`, каждый [Ccw](Direction::Ccw) —
+/// вычитает его. Позиция удерживается в диапазоне `[min, max]` согласно
+/// выбранной политике [OutOfRange], что подходит как для ограниченных
+/// ползунков, так и для бесконечных круговых меню.
+pub struct Counter {
+    position: i32,
+    min: i32,
+    max: i32,
+    step: i32,
+    policy: OutOfRange,
+    changed: bool,
+}
+
+impl Counter {
+    pub fn new(min: i32, max: i32, policy: OutOfRange) -> Self {
+        Self {
+            position: min,
+            min,
+            max,
+            step: 1,
+            policy,
+            changed: false,
+        }
+    }
+
+    /// Размер шага на один детент (по умолчанию `1`).
+    pub fn set_step(&mut self, step: i32) {
+        self.step = step;
+    }
+
+    /// Применяет одно направление к позиции.
+    pub fn update(&mut self, direction: Direction) {
+        let delta = match direction {
+            Direction::Cw => self.step,
+            Direction::Ccw => -self.step,
+            Direction::None => {
+                self.changed = false;
+                return;
+            }
+        };
+
+        let old = self.position;
+        self.position = self.apply(old + delta);
+        self.changed = self.position != old;
+    }
+
+    /// Приводит значение в диапазон согласно политике [OutOfRange].
+    fn apply(&self, value: i32) -> i32 {
+        match self.policy {
+            OutOfRange::Clamp => value.clamp(self.min, self.max),
+            OutOfRange::Wrap => {
+                let span = self.max - self.min + 1;
+                self.min + (value - self.min).rem_euclid(span)
+            }
+        }
+    }
+
+    /// Текущая позиция.
+    #[inline(always)]
+    pub fn position(&self) -> i32 {
+        self.position
+    }
+
+    /// Явно задаёт позицию, приводя её в диапазон.
+    pub fn set_position(&mut self, position: i32) {
+        let old = self.position;
+        self.position = self.apply(position);
+        self.changed = self.position != old;
+    }
+
+    /// true только на тех тиках, где позиция действительно изменилась.
+    #[inline(always)]
+    pub fn changed(&self) -> bool {
+        self.changed
+    }
+}